@@ -1,11 +1,18 @@
 //! A zero-setup commandline argument parser.
 //!
-//! Parse arguments with [parse_arguments].
+//! Parse arguments with [parse_arguments], or [parse_arguments_into] for a
+//! [ParsedArgs] with typed accessors. An optional [Schema] can validate the
+//! result and render `--help` text.
+
+use std::str::FromStr;
+
+mod schema;
+pub use schema::Schema;
 
 /// A parsed command line argument.
 ///
 /// An argument is an operand, attribute or flag.
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Argument {
     Operand(String),
     Attribute(String, String),
@@ -16,6 +23,10 @@ pub enum Argument {
 pub enum Error {
     EmptyAttribute,
     EmptyFlag,
+    UnknownFlag(String),
+    UnknownAttribute(String),
+    AttributeMissingValue(String),
+    MissingOperand(String),
 }
 
 /// A character iterator with 1 character lookahead.
@@ -44,9 +55,27 @@ impl <I: Iterator<Item=char>> CharIter<I> {
 /// Parse command line arguments.
 ///
 /// Note that the first argument is often the command or binary name.
-pub fn parse_arguments<T: Iterator<Item=String>>(mut args: T) -> Result<Vec<Argument>, Error> {
+///
+/// A bare `-` or `+` is treated as an operand (a lone `-` conventionally
+/// means stdin or stdout), not as an end-of-options marker. A bare `--`
+/// flips parsing into an operands-only mode: the `--` itself is dropped,
+/// and every token after it, including ones starting with `-` or `+`, is
+/// pushed verbatim as an [Argument::Operand].
+pub fn parse_arguments<T: Iterator<Item=String>>(args: T) -> Result<Vec<Argument>, Error> {
+    parse_arguments_with_schema(args, None)
+}
+
+/// Parses like [parse_arguments], but consults `schema` when splitting a
+/// clustered short option like `-xvo` so a value-taking option can claim
+/// the rest of the token (see [Schema::attribute] and [parse_short_options]).
+fn parse_arguments_with_schema<T: Iterator<Item=String>>(mut args: T, schema: Option<&Schema>) -> Result<Vec<Argument>, Error> {
     let mut arguments = vec![];
-    for arg in &mut args {
+    let mut operands_only = false;
+    while let Some(arg) = args.next() {
+        if operands_only {
+            arguments.push(Argument::Operand(arg));
+            continue;
+        }
         let mut iter = CharIter::new(arg.chars());
         if let Some(c) = iter.current() {
             if c == '-' {
@@ -57,20 +86,20 @@ pub fn parse_arguments<T: Iterator<Item=String>>(mut args: T) -> Result<Vec<Argu
                         if let Some(_) = iter.current() {
                             parse_option(&mut arguments, &mut iter)?;
                         } else {
-                            break;
+                            operands_only = true;
                         }
                     } else {
-                        parse_short_options(&mut arguments, &mut iter)?;
+                        parse_short_options(&mut arguments, &mut iter, schema, &mut args)?;
                     }
                 } else {
-                    break;
+                    arguments.push(Argument::Operand(arg));
                 }
             } else if c == '+' {
                 iter.next();
                 if let Some(_) = iter.current() {
                     parse_option(&mut arguments, &mut iter)?;
                 } else {
-                    break;
+                    arguments.push(Argument::Operand(arg));
                 }
             } else {
                 parse_argument(&mut arguments, &mut iter)?;
@@ -79,19 +108,42 @@ pub fn parse_arguments<T: Iterator<Item=String>>(mut args: T) -> Result<Vec<Argu
             arguments.push(Argument::Operand(String::new()));
         }
     }
-    for arg in &mut args {
-        arguments.push(Argument::Operand(arg));
-    }
     Ok(arguments)
 }
 
-/// Parse a short option.
-fn parse_short_options<I: Iterator<Item=char>>(arguments: &mut Vec<Argument>, iter: &mut CharIter<I>) -> Result<(), Error> {
+/// Parse a short option, or a cluster of them (e.g. `-xvo`).
+///
+/// Without a `schema`, every character becomes its own [Argument::Flag],
+/// except that a trailing `=value` still attaches to the last one. With a
+/// `schema`, the first character declared as a value-taking attribute ends
+/// the cluster and claims the remainder of the token (minus a separating
+/// `=`, if present) as its value. If nothing follows it in the token and no
+/// `=` was given, the next token in `args` is consumed as the value (the
+/// common getopts `-o value` form); if there is no next token either, the
+/// option is pushed as a plain [Argument::Flag] so a [Schema::validate] pass
+/// reports the missing value instead of a silently fabricated empty string.
+fn parse_short_options<I: Iterator<Item=char>, T: Iterator<Item=String>>(arguments: &mut Vec<Argument>, iter: &mut CharIter<I>, schema: Option<&Schema>, args: &mut T) -> Result<(), Error> {
     loop {
         if let Some(c) = iter.current() {
             iter.next();
             if c == '=' {
                 return Err(Error::EmptyAttribute);
+            } else if let Some(name) = schema.and_then(|s| s.short_attribute(c)) {
+                let name = name.to_string();
+                let had_equals = iter.current() == Some('=');
+                if had_equals {
+                    iter.next();
+                }
+                if iter.current().is_some() {
+                    parse_attribute_value(arguments, iter, name)?;
+                } else if had_equals {
+                    arguments.push(Argument::Attribute(name, String::new()));
+                } else if let Some(value) = args.next() {
+                    arguments.push(Argument::Attribute(name, value));
+                } else {
+                    arguments.push(Argument::Flag(name));
+                }
+                break;
             } else {
                 if let Some(d) = iter.current() {
                     if d == '=' {
@@ -203,6 +255,232 @@ fn parse_attribute_value<I: Iterator<Item=char>>(arguments: &mut Vec<Argument>,
     Ok(())
 }
 
+/// Parse command line arguments into a [ParsedArgs].
+///
+/// Equivalent to calling [parse_arguments] and converting the result with
+/// [ParsedArgs::from].
+pub fn parse_arguments_into<T: Iterator<Item=String>>(args: T) -> Result<ParsedArgs, Error> {
+    parse_arguments(args).map(ParsedArgs::from)
+}
+
+/// A typed view over a parsed argument list.
+///
+/// Wraps the flat [Vec<Argument>] returned by [parse_arguments] and exposes
+/// accessors for pulling flags, attributes and operands out without having
+/// to loop-and-match by hand.
+pub struct ParsedArgs {
+    arguments: Vec<Argument>,
+    operands: Vec<String>,
+}
+
+impl From<Vec<Argument>> for ParsedArgs {
+    fn from(arguments: Vec<Argument>) -> Self {
+        let operands = arguments.iter()
+            .filter_map(|a| match a {
+                Argument::Operand(o) => Some(o.clone()),
+                _ => None,
+            })
+            .collect();
+        ParsedArgs { arguments, operands }
+    }
+}
+
+impl ParsedArgs {
+
+    /// Returns true if `name` occurs as a flag at least once.
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.arguments.iter().any(|a| matches!(a, Argument::Flag(f) if f == name))
+    }
+
+    /// Counts how many times `name` occurs as a flag, so `-vvv` yields 3.
+    pub fn flag_count(&self, name: &str) -> usize {
+        self.arguments.iter().filter(|a| matches!(a, Argument::Flag(f) if f == name)).count()
+    }
+
+    /// Returns the value of `name`'s last occurrence as an attribute.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.arguments.iter().rev().find_map(|a| match a {
+            Argument::Attribute(k, v) if k == name => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Parses `name`'s last occurrence as an attribute with [FromStr].
+    ///
+    /// Returns `Ok(None)` if the attribute is absent, and bubbles up the
+    /// `FromStr` error if the value fails to parse.
+    pub fn attribute_as<T: FromStr>(&self, name: &str) -> Result<Option<T>, T::Err> {
+        self.attribute(name).map(str::parse).transpose()
+    }
+
+    /// Returns every value given for `name` as an attribute, in order.
+    pub fn attributes<'a>(&'a self, name: &'a str) -> impl Iterator<Item=&'a str> {
+        self.arguments.iter().filter_map(move |a| match a {
+            Argument::Attribute(k, v) if k == name => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the operands, in order.
+    pub fn operands(&self) -> &[String] {
+        &self.operands
+    }
+
+    /// Returns the underlying arguments, in parse order.
+    pub(crate) fn arguments(&self) -> &[Argument] {
+        &self.arguments
+    }
+
+    /// Fills in attributes from environment variables where the command line didn't provide one.
+    ///
+    /// `mapping` pairs an attribute name with the environment variable to
+    /// fall back to. Command-line values always take precedence: an
+    /// attribute already present is left untouched. If the environment
+    /// variable is unset too, the attribute stays absent.
+    pub fn fill_from_env(&mut self, mapping: &[(&str, &str)]) {
+        for (name, var) in mapping {
+            if self.attribute(name).is_some() {
+                continue;
+            }
+            if let Ok(value) = std::env::var(var) {
+                self.arguments.push(Argument::Attribute((*name).to_string(), value));
+            }
+        }
+    }
+
+}
+
+/// Splits `args` around its first subcommand, `git`-style.
+///
+/// Options and attributes up to the first non-option [Argument::Operand]
+/// are parsed and returned as the top-level arguments. That operand is
+/// taken as the subcommand name, and every token at and after it — including
+/// its own flags — is returned untouched so it can be fed back through
+/// [parse_arguments] (or [Schema::parse]) with a subcommand-specific schema.
+/// A bare `--` is consumed as the options delimiter it is in
+/// [parse_arguments], not treated as the subcommand itself, so the token
+/// right after it is taken as the subcommand name.
+///
+/// Returns [Error::MissingOperand] if `args` is exhausted before a
+/// subcommand is found.
+pub fn split_subcommand<T: Iterator<Item=String>>(mut args: T) -> Result<(Vec<Argument>, String, Vec<String>), Error> {
+    let mut arguments = vec![];
+    while let Some(arg) = args.next() {
+        let mut iter = CharIter::new(arg.chars());
+        match iter.current() {
+            Some('-') => {
+                iter.next();
+                match iter.current() {
+                    Some('-') => {
+                        iter.next();
+                        if iter.current().is_some() {
+                            parse_option(&mut arguments, &mut iter)?;
+                        } else {
+                            // Bare `--`: per parse_arguments, it is an
+                            // options delimiter, not an operand itself, so
+                            // the subcommand is whatever follows it.
+                            return match args.next() {
+                                Some(subcommand) => {
+                                    let tail = std::iter::once(subcommand.clone()).chain(args).collect();
+                                    Ok((arguments, subcommand, tail))
+                                }
+                                None => Err(Error::MissingOperand("subcommand".into())),
+                            };
+                        }
+                    }
+                    None => {
+                        let tail = std::iter::once(arg.clone()).chain(args).collect();
+                        return Ok((arguments, arg, tail));
+                    }
+                    Some(_) => parse_short_options(&mut arguments, &mut iter, None, &mut args)?,
+                }
+            }
+            Some('+') => {
+                iter.next();
+                if iter.current().is_some() {
+                    parse_option(&mut arguments, &mut iter)?;
+                } else {
+                    let tail = std::iter::once(arg.clone()).chain(args).collect();
+                    return Ok((arguments, arg, tail));
+                }
+            }
+            _ => {
+                parse_argument(&mut arguments, &mut iter)?;
+                if let Some(Argument::Operand(_)) = arguments.last() {
+                    arguments.pop();
+                    let tail = std::iter::once(arg.clone()).chain(args).collect();
+                    return Ok((arguments, arg, tail));
+                }
+            }
+        }
+    }
+    Err(Error::MissingOperand("subcommand".into()))
+}
+
+#[test]
+fn lone_dash_is_an_operand() {
+    let args = vec!["-".to_string(), "file".to_string()].into_iter();
+    let result = parse_arguments(args).unwrap();
+    assert_eq!(result, vec![
+        Argument::Operand("-".into()),
+        Argument::Operand("file".into()),
+    ]);
+}
+
+#[test]
+fn lone_plus_is_an_operand() {
+    let args = vec!["+".to_string(), "file".to_string()].into_iter();
+    let result = parse_arguments(args).unwrap();
+    assert_eq!(result, vec![
+        Argument::Operand("+".into()),
+        Argument::Operand("file".into()),
+    ]);
+}
+
+#[test]
+fn double_dash_switches_to_operands_only() {
+    let args = vec!["--flag".to_string(), "--".to_string(), "--not-a-flag".to_string(), "-x".to_string()].into_iter();
+    let result = parse_arguments(args).unwrap();
+    assert_eq!(result, vec![
+        Argument::Flag("flag".into()),
+        Argument::Operand("--not-a-flag".into()),
+        Argument::Operand("-x".into()),
+    ]);
+}
+
+#[test]
+fn split_subcommand_separates_global_options_from_the_subcommand_tail() {
+    let args = vec!["-v".to_string(), "deploy".to_string(), "--force".to_string(), "host".to_string()].into_iter();
+    let (global, subcommand, tail) = split_subcommand(args).unwrap();
+    assert_eq!(global, vec![Argument::Flag("v".into())]);
+    assert_eq!(subcommand, "deploy");
+    assert_eq!(tail, vec!["deploy".to_string(), "--force".to_string(), "host".to_string()]);
+}
+
+#[test]
+fn split_subcommand_treats_a_lone_plus_as_the_subcommand() {
+    let args = vec!["+".to_string(), "arg".to_string()].into_iter();
+    let (global, subcommand, tail) = split_subcommand(args).unwrap();
+    assert_eq!(global, vec![]);
+    assert_eq!(subcommand, "+");
+    assert_eq!(tail, vec!["+".to_string(), "arg".to_string()]);
+}
+
+#[test]
+fn split_subcommand_hands_off_the_token_after_a_bare_double_dash() {
+    let args = vec!["--verbose".to_string(), "--".to_string(), "deploy".to_string(), "--force".to_string()].into_iter();
+    let (global, subcommand, tail) = split_subcommand(args).unwrap();
+    assert_eq!(global, vec![Argument::Flag("verbose".into())]);
+    assert_eq!(subcommand, "deploy");
+    assert_eq!(tail, vec!["deploy".to_string(), "--force".to_string()]);
+}
+
+#[test]
+fn split_subcommand_errors_when_no_subcommand_is_found() {
+    let args = vec!["-v".to_string()].into_iter();
+    assert!(matches!(split_subcommand(args), Err(Error::MissingOperand(name)) if name == "subcommand"));
+}
+
 #[test]
 #[ignore]
 fn test_main() {
@@ -0,0 +1,303 @@
+//! An opt-in declarative schema for validating parsed arguments and
+//! rendering `--help` text.
+//!
+//! The base parser stays zero-setup: a [Schema] is purely additive and is
+//! only consulted if a caller builds one and applies it.
+
+use crate::{parse_arguments_with_schema, Argument, Error, ParsedArgs};
+
+/// The width, in columns, reserved for an option's name column in [Schema::help].
+const OPTION_WIDTH: usize = 24;
+
+/// The total width, in columns, that [Schema::help] wraps description text to.
+const TOTAL_WIDTH: usize = 79;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OptionKind {
+    Flag,
+    Attribute,
+}
+
+struct OptionSpec {
+    name: String,
+    short: char,
+    description: String,
+    kind: OptionKind,
+}
+
+/// A declarative description of the flags, attributes and operands a command accepts.
+///
+/// Build one with [Schema::new] and the `flag`/`attribute`/`operand` methods,
+/// then check a [ParsedArgs] against it with [Schema::validate] and render
+/// usage text with [Schema::help].
+pub struct Schema {
+    options: Vec<OptionSpec>,
+    operands: Vec<(String, String)>,
+}
+
+impl Schema {
+
+    /// Creates an empty schema.
+    pub fn new() -> Self {
+        Schema { options: vec![], operands: vec![] }
+    }
+
+    /// Declares a flag with a long `name`, a `short` single-character alias, and a `description`.
+    pub fn flag(mut self, name: &str, short: char, description: &str) -> Self {
+        self.options.push(OptionSpec { name: name.into(), short, description: description.into(), kind: OptionKind::Flag });
+        self
+    }
+
+    /// Declares a value-taking attribute with a long `name`, a `short` single-character alias, and a `description`.
+    pub fn attribute(mut self, name: &str, short: char, description: &str) -> Self {
+        self.options.push(OptionSpec { name: name.into(), short, description: description.into(), kind: OptionKind::Attribute });
+        self
+    }
+
+    /// Declares a required operand with a `name` and a `description`.
+    pub fn operand(mut self, name: &str, description: &str) -> Self {
+        self.operands.push((name.into(), description.into()));
+        self
+    }
+
+    /// Parses `args` like [crate::parse_arguments], but lets this schema
+    /// decide how a clustered short option attaches a trailing value (see
+    /// [Schema::attribute]).
+    pub fn parse<T: Iterator<Item=String>>(&self, args: T) -> Result<Vec<Argument>, Error> {
+        parse_arguments_with_schema(args, Some(self))
+    }
+
+    /// Returns the long name of the value-taking attribute aliased by short option `c`, if any.
+    pub(crate) fn short_attribute(&self, c: char) -> Option<&str> {
+        self.options.iter()
+            .find(|o| o.short == c && o.kind == OptionKind::Attribute)
+            .map(|o| o.name.as_str())
+    }
+
+    /// Finds the declared option matching `token`, whether `token` is a long name or a single-character short alias.
+    fn find(&self, token: &str) -> Option<&OptionSpec> {
+        let mut chars = token.chars();
+        let short = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        };
+        self.options.iter().find(|o| o.name == token || short == Some(o.short))
+    }
+
+    /// Validates `parsed` against this schema.
+    ///
+    /// Reports unknown flags and attributes, attributes given without a
+    /// value (i.e. used as a bare flag), and operands missing from the
+    /// command line.
+    pub fn validate(&self, parsed: &ParsedArgs) -> Result<(), Error> {
+        for argument in parsed.arguments() {
+            match argument {
+                Argument::Flag(name) => match self.find(name) {
+                    Some(spec) if spec.kind == OptionKind::Attribute => return Err(Error::AttributeMissingValue(spec.name.clone())),
+                    Some(_) => {}
+                    None => return Err(Error::UnknownFlag(name.clone())),
+                },
+                Argument::Attribute(name, _) => match self.find(name) {
+                    Some(_) => {}
+                    None => return Err(Error::UnknownAttribute(name.clone())),
+                },
+                Argument::Operand(_) => {}
+            }
+        }
+        if let Some((name, _)) = self.operands.get(parsed.operands().len()) {
+            return Err(Error::MissingOperand(name.clone()));
+        }
+        Ok(())
+    }
+
+    /// Validates `parsed` against this schema, returning it unchanged on success.
+    pub fn apply(&self, parsed: ParsedArgs) -> Result<ParsedArgs, Error> {
+        self.validate(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Renders usage and `--help` text for this schema, naming the command as `program`.
+    pub fn help(&self, program: &str) -> String {
+        let mut out = String::new();
+        out.push_str("Usage: ");
+        out.push_str(program);
+        if !self.options.is_empty() {
+            out.push_str(" [options]");
+        }
+        for (name, _) in &self.operands {
+            out.push_str(" <");
+            out.push_str(name);
+            out.push('>');
+        }
+        out.push('\n');
+        if !self.options.is_empty() {
+            out.push_str("\nOptions:\n");
+            for option in &self.options {
+                let header = format!("  -{}, --{}", option.short, option.name);
+                out.push_str(&format_entry(&header, &option.description));
+            }
+        }
+        if !self.operands.is_empty() {
+            out.push_str("\nOperands:\n");
+            for (name, description) in &self.operands {
+                let header = format!("  {}", name);
+                out.push_str(&format_entry(&header, description));
+            }
+        }
+        out
+    }
+
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema::new()
+    }
+}
+
+/// Lays out one `header`/`description` pair in [Schema::help]'s two-column format.
+///
+/// `header` is left-justified to [OPTION_WIDTH], or given its own line if
+/// it doesn't fit. `description` is wrapped on whitespace to [TOTAL_WIDTH],
+/// with continuation lines indented to the option column.
+fn format_entry(header: &str, description: &str) -> String {
+    let mut out = String::new();
+    let wrap_width = TOTAL_WIDTH - OPTION_WIDTH;
+    if header.len() + 2 > OPTION_WIDTH {
+        out.push_str(header);
+        out.push('\n');
+        out.push_str(&" ".repeat(OPTION_WIDTH));
+    } else {
+        out.push_str(header);
+        out.push_str(&" ".repeat(OPTION_WIDTH - header.len()));
+    }
+    let mut column = 0;
+    let mut first = true;
+    for word in description.split_whitespace() {
+        if !first && column + 1 + word.len() > wrap_width {
+            out.push('\n');
+            out.push_str(&" ".repeat(OPTION_WIDTH));
+            column = 0;
+            first = true;
+        }
+        if !first {
+            out.push(' ');
+            column += 1;
+        }
+        out.push_str(word);
+        column += word.len();
+        first = false;
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_accepts_a_matching_command_line() {
+        let schema = Schema::new()
+            .flag("verbose", 'v', "be noisy")
+            .attribute("output", 'o', "where to write")
+            .operand("file", "the file to read");
+        let parsed = ParsedArgs::from(vec![
+            Argument::Flag("verbose".into()),
+            Argument::Attribute("output".into(), "out.txt".into()),
+            Argument::Operand("in.txt".into()),
+        ]);
+        assert!(schema.validate(&parsed).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_an_unknown_flag() {
+        let schema = Schema::new().flag("verbose", 'v', "be noisy");
+        let parsed = ParsedArgs::from(vec![Argument::Flag("quiet".into())]);
+        assert!(matches!(schema.validate(&parsed), Err(Error::UnknownFlag(name)) if name == "quiet"));
+    }
+
+    #[test]
+    fn validate_rejects_an_unknown_attribute() {
+        let schema = Schema::new().attribute("output", 'o', "where to write");
+        let parsed = ParsedArgs::from(vec![Argument::Attribute("input".into(), "in.txt".into())]);
+        assert!(matches!(schema.validate(&parsed), Err(Error::UnknownAttribute(name)) if name == "input"));
+    }
+
+    #[test]
+    fn validate_rejects_an_attribute_used_as_a_bare_flag() {
+        let schema = Schema::new().attribute("output", 'o', "where to write");
+        let parsed = ParsedArgs::from(vec![Argument::Flag("output".into())]);
+        assert!(matches!(schema.validate(&parsed), Err(Error::AttributeMissingValue(name)) if name == "output"));
+    }
+
+    #[test]
+    fn validate_rejects_a_missing_operand() {
+        let schema = Schema::new().operand("file", "the file to read");
+        let parsed = ParsedArgs::from(vec![]);
+        assert!(matches!(schema.validate(&parsed), Err(Error::MissingOperand(name)) if name == "file"));
+    }
+
+    #[test]
+    fn parse_attaches_a_clustered_short_option_value() {
+        let schema = Schema::new().attribute("output", 'o', "where to write");
+        let args = vec!["-voout.txt".to_string()].into_iter();
+        let result = schema.parse(args).unwrap();
+        assert_eq!(result, vec![
+            Argument::Flag("v".into()),
+            Argument::Attribute("output".into(), "out.txt".into()),
+        ]);
+    }
+
+    #[test]
+    fn parse_attaches_a_short_option_value_after_equals() {
+        let schema = Schema::new().attribute("output", 'o', "where to write");
+        let args = vec!["-o=out.txt".to_string()].into_iter();
+        let result = schema.parse(args).unwrap();
+        assert_eq!(result, vec![Argument::Attribute("output".into(), "out.txt".into())]);
+    }
+
+    #[test]
+    fn parse_consumes_the_next_token_for_a_dangling_short_option_value() {
+        let schema = Schema::new().attribute("output", 'o', "where to write");
+        let args = vec!["-o".to_string(), "out.txt".to_string()].into_iter();
+        let result = schema.parse(args).unwrap();
+        assert_eq!(result, vec![Argument::Attribute("output".into(), "out.txt".into())]);
+    }
+
+    #[test]
+    fn parse_falls_back_to_a_flag_when_no_value_is_available() {
+        let schema = Schema::new().attribute("output", 'o', "where to write");
+        let args = vec!["-o".to_string()].into_iter();
+        let result = schema.parse(args).unwrap();
+        assert_eq!(result, vec![Argument::Flag("output".into())]);
+    }
+
+    #[test]
+    fn help_keeps_a_short_description_on_the_header_line() {
+        let header = "  -v, --v";
+        let expected = format!("{}{}be noisy\n", header, " ".repeat(OPTION_WIDTH - header.len()));
+        assert_eq!(format_entry(header, "be noisy"), expected);
+    }
+
+    #[test]
+    fn help_wraps_a_description_past_total_width() {
+        // wrap_width = TOTAL_WIDTH - OPTION_WIDTH = 55; two 27-char words plus
+        // a joining space is 55 (fits), a third word pushes past the boundary.
+        let word = "a".repeat(27);
+        let description = format!("{} {} {}", word, word, word);
+        let header = "  -v, --verbose";
+        let rendered = format_entry(header, &description);
+        let lines: Vec<&str> = rendered.trim_end_matches('\n').split('\n').collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], format!("{}{}", header, " ".repeat(OPTION_WIDTH - header.len())) + &format!("{} {}", word, word));
+        assert_eq!(lines[1], format!("{}{}", " ".repeat(OPTION_WIDTH), word));
+    }
+
+    #[test]
+    fn help_gives_an_overlong_header_its_own_line() {
+        let schema = Schema::new().flag("extremely-long-option-name", 'x', "does a thing");
+        let rendered = schema.help("tool");
+        assert!(rendered.contains("  -x, --extremely-long-option-name\n"));
+    }
+}